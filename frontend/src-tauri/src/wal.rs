@@ -6,24 +6,73 @@
 //!
 //! # Design
 //!
-//! The WAL uses a simple file-based approach:
-//! 1. Before operation: Write WAL entry with backup data
-//! 2. During operation: Update status to InProgress
-//! 3. After success: Delete WAL file (commit)
-//! 4. On failure/crash: Restore from backup in WAL
+//! The WAL is an append-only record log, not a single rewritten file. Every
+//! mutation to a transaction's state (`Begin`, `StatusUpdate`, `Commit`,
+//! `RolledBack`, `Checkpoint`) is appended as its own numbered record
+//! tagged with the transaction's id:
+//! 1. Before operation: append a `Begin` record with backup data
+//! 2. During operation: append a `StatusUpdate` record
+//! 3. After success: append a `Commit` record
+//! 4. On failure/crash: append a `RolledBack` record after restoring from backup
+//!
+//! Records are never rewritten in place, only appended, so a torn trailing
+//! record (e.g. a partial write at crash time) can always be discarded
+//! without corrupting any earlier, already-durable record. This also lets
+//! several vault operations have records in flight at once, recovered
+//! independently of one another.
+//!
+//! All I/O goes through `tokio::fs` so a vault operation never blocks the
+//! Tauri command thread; `recover_incomplete` is `await`ed from the async
+//! `setup` hook instead of blocking app startup. A process-wide lock
+//! serializes writers so concurrent async callers can't interleave
+//! appends to the same log.
+//!
+//! Every transaction that commits is also archived to a `history/`
+//! directory (one file per UTC day) before its WAL records are compacted
+//! away, so `list_history` can show an audit trail and
+//! `restore_from_history` can undo an accidental destructive operation
+//! after the fact. The archive is pruned to a configurable retention
+//! policy ([`HistoryRetention`]) every time a new entry is archived.
+//!
+//! Long-running, many-item operations (currently `CleanupBrokenVaults`)
+//! can also call [`checkpoint`] to periodically persist which
+//! `vault_ids` have already been processed. Checkpoints are throttled by
+//! a [`CheckpointConfig`] interval so a tight loop doesn't fsync on
+//! every item; recovery folds the most recent checkpoint back into the
+//! entry so `rollback_operation` only needs to undo the unfinished tail
+//! of the batch instead of the whole thing.
 //!
 //! # Safety
 //!
-//! - All writes are synced to disk before proceeding
-//! - Registry backups are stored in the WAL entry
+//! - All writes are synced to disk (awaiting `File::sync_all`) before proceeding
+//! - Registry backups are stored in the `Begin` record of each transaction
+//! - Every record is wrapped in a CRC32 envelope so a torn write from a
+//!   crash mid-append is detected and discarded rather than corrupting
+//!   recovery
+//! - Registry backups are hashed with SHA-256 at `begin_transaction` time
+//!   and re-verified before rollback ever restores them, so a corrupted
+//!   backup can't clobber the live registry
 //! - Recovery happens automatically on startup
 
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use std::fs::{self, File};
-use std::io::Write;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::sync::LazyLock;
 use tauri::Manager;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex as AsyncMutex;
+
+/// Serializes all writers to the WAL log so that two concurrent async
+/// callers can never interleave appends (e.g. both reading the same
+/// "next sequence number" and racing to write it).
+static WAL_LOCK: LazyLock<AsyncMutex<()>> = LazyLock::new(|| AsyncMutex::new(()));
+
+/// Last time each in-flight transaction was checkpointed, so [`checkpoint`]
+/// can throttle itself to [`CheckpointConfig::interval`] instead of fsyncing on
+/// every processed item.
+static LAST_CHECKPOINT: LazyLock<std::sync::Mutex<HashMap<String, std::time::Instant>>> =
+    LazyLock::new(|| std::sync::Mutex::new(HashMap::new()));
 
 /// Operations that can be tracked by WAL
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -36,6 +85,11 @@ pub enum WalOperation {
         delete_files: bool,
         /// JSON backup of registry before operation
         registry_backup: String,
+        /// SHA-256 hex digest of `registry_backup`, filled in at
+        /// `begin_transaction` time and re-checked before it is ever
+        /// restored
+        #[serde(default)]
+        registry_backup_hash: String,
     },
     /// Vault creation
     CreateVault {
@@ -46,11 +100,47 @@ pub enum WalOperation {
     CleanupBrokenVaults {
         /// JSON backup of registry before operation
         registry_backup: String,
+        /// SHA-256 hex digest of `registry_backup`, filled in at
+        /// `begin_transaction` time and re-checked before it is ever
+        /// restored
+        #[serde(default)]
+        registry_backup_hash: String,
         /// IDs of vaults being removed
         vault_ids: Vec<String>,
+        /// IDs from `vault_ids` that have already been processed, as of
+        /// the last [`checkpoint`] call. Lets recovery restore only the
+        /// unfinished portion of a large cleanup instead of reverting the
+        /// whole batch.
+        #[serde(default)]
+        processed_vault_ids: Vec<String>,
     },
 }
 
+impl WalOperation {
+    /// Compute and fill in the integrity hash over this operation's
+    /// registry backup, if it carries one. Called once, at
+    /// [`begin_transaction`] time, so every persisted operation already
+    /// has a hash to verify against before rollback ever trusts its backup.
+    fn with_backup_hash(mut self) -> Self {
+        match &mut self {
+            WalOperation::DeleteVault {
+                registry_backup,
+                registry_backup_hash,
+                ..
+            }
+            | WalOperation::CleanupBrokenVaults {
+                registry_backup,
+                registry_backup_hash,
+                ..
+            } => {
+                *registry_backup_hash = sha256_hex(registry_backup);
+            }
+            WalOperation::CreateVault { .. } => {}
+        }
+        self
+    }
+}
+
 /// Status of a WAL entry
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -65,7 +155,51 @@ pub enum WalStatus {
     RolledBack,
 }
 
-/// A single WAL entry
+/// A single append-only record in the WAL log.
+///
+/// Each record carries the id of the transaction it belongs to, so
+/// recovery can group records by transaction and reconstruct the latest
+/// known state of each one independently.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum WalRecord {
+    /// A transaction is starting; carries the full operation payload.
+    Begin {
+        txn_id: String,
+        operation: WalOperation,
+        started_at: String,
+    },
+    /// The transaction's status changed (e.g. Pending -> InProgress).
+    StatusUpdate { txn_id: String, status: WalStatus },
+    /// The transaction completed successfully.
+    Commit { txn_id: String },
+    /// The transaction was rolled back after failure or crash recovery.
+    RolledBack { txn_id: String, error: Option<String> },
+    /// Progress checkpoint for a long-running operation, persisted at most
+    /// once per [`CheckpointConfig::interval`] so we don't fsync on every item.
+    Checkpoint {
+        txn_id: String,
+        processed_vault_ids: Vec<String>,
+    },
+}
+
+impl WalRecord {
+    /// The transaction this record belongs to.
+    fn txn_id(&self) -> &str {
+        match self {
+            WalRecord::Begin { txn_id, .. }
+            | WalRecord::StatusUpdate { txn_id, .. }
+            | WalRecord::Commit { txn_id }
+            | WalRecord::RolledBack { txn_id, .. }
+            | WalRecord::Checkpoint { txn_id, .. } => txn_id,
+        }
+    }
+}
+
+/// A single transaction's state, reconstructed from its records.
+///
+/// This is the reconstructed view handed back to callers; it is never
+/// itself persisted directly (the log stores [`WalRecord`]s instead).
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct WalEntry {
     /// Unique identifier for this transaction
@@ -90,15 +224,125 @@ pub struct RecoveryResult {
     pub message: Option<String>,
     /// The operation that was recovered from
     pub operation_type: Option<String>,
+    /// True when a registry backup failed its integrity check during
+    /// rollback, so the restore was aborted to avoid clobbering the live
+    /// registry. The UI should warn the user rather than treat this like a
+    /// normal, silent recovery.
+    #[serde(default)]
+    pub integrity_failure: bool,
+}
+
+/// Retention policy for the committed-transaction history archive.
+#[derive(Clone, Copy, Debug)]
+pub struct HistoryRetention {
+    /// Keep at most this many history entries, dropping the oldest first.
+    pub max_entries: usize,
+    /// Drop history entries older than this many days.
+    pub max_age_days: i64,
+}
+
+impl Default for HistoryRetention {
+    fn default() -> Self {
+        Self {
+            max_entries: 500,
+            max_age_days: 90,
+        }
+    }
+}
+
+/// How often [`checkpoint`] is allowed to persist progress for a given
+/// transaction.
+#[derive(Clone, Copy, Debug)]
+pub struct CheckpointConfig {
+    pub interval: std::time::Duration,
+}
+
+impl Default for CheckpointConfig {
+    fn default() -> Self {
+        Self {
+            interval: std::time::Duration::from_millis(500),
+        }
+    }
 }
 
-/// Get path to the WAL file
-fn get_wal_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+/// Compute the SHA-256 hex digest of `data`.
+fn sha256_hex(data: &str) -> String {
+    use sha2::{Digest, Sha256};
+    let mut hasher = Sha256::new();
+    hasher.update(data.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Check whether a registry backup can be trusted before it is restored:
+/// its hash must match what was recorded at `begin_transaction` time, and
+/// it must actually parse as valid JSON.
+fn backup_is_trustworthy(registry_backup: &str, registry_backup_hash: &str) -> bool {
+    sha256_hex(registry_backup) == registry_backup_hash
+        && serde_json::from_str::<serde_json::Value>(registry_backup).is_ok()
+}
+
+/// Re-apply a `CleanupBrokenVaults` checkpoint to its pre-operation
+/// registry backup: the backup snapshot still contains entries for vaults
+/// that were already removed (and checkpointed) before a crash, so
+/// restoring it verbatim would resurrect work the cleanup had already
+/// finished. Strip `processed_vault_ids` back out of the backup so rollback
+/// reverts only the unfinished tail of the batch.
+///
+/// Falls back to returning the backup unchanged if its shape isn't a plain
+/// array or an object with a `vaults` array, since this module only ever
+/// treats the registry as an opaque JSON blob.
+fn apply_checkpoint_to_backup(registry_backup: &str, processed_vault_ids: &[String]) -> String {
+    if processed_vault_ids.is_empty() {
+        return registry_backup.to_string();
+    }
+
+    let Ok(mut value) = serde_json::from_str::<serde_json::Value>(registry_backup) else {
+        return registry_backup.to_string();
+    };
+
+    let processed: HashSet<&str> = processed_vault_ids.iter().map(String::as_str).collect();
+    let is_processed = |entry: &serde_json::Value| -> bool {
+        if let Some(id) = entry.as_str() {
+            return processed.contains(id);
+        }
+        entry
+            .get("id")
+            .or_else(|| entry.get("vault_id"))
+            .and_then(|v| v.as_str())
+            .map(|id| processed.contains(id))
+            .unwrap_or(false)
+    };
+
+    let vaults = match &mut value {
+        serde_json::Value::Array(vaults) => Some(vaults),
+        serde_json::Value::Object(map) => map.get_mut("vaults").and_then(|v| v.as_array_mut()),
+        _ => None,
+    };
+
+    let Some(vaults) = vaults else {
+        return registry_backup.to_string();
+    };
+    vaults.retain(|entry| !is_processed(entry));
+
+    serde_json::to_string(&value).unwrap_or_else(|_| registry_backup.to_string())
+}
+
+/// Get path to the WAL record directory
+fn get_wal_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let app_data = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    Ok(app_data.join("wal"))
+}
+
+/// Get path to the committed-transaction history archive directory
+fn get_history_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
     let app_data = app
         .path()
         .app_data_dir()
         .map_err(|e| format!("Failed to get app data dir: {}", e))?;
-    Ok(app_data.join("operation.wal"))
+    Ok(app_data.join("history"))
 }
 
 /// Get path to the registry file
@@ -110,192 +354,571 @@ fn get_registry_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
     Ok(app_data.join("vaults.json"))
 }
 
+/// Whether `path` exists, without blocking the async runtime.
+async fn path_exists(path: &PathBuf) -> bool {
+    tokio::fs::try_exists(path).await.unwrap_or(false)
+}
+
 /// Sync file to disk for durability
-fn sync_file(path: &PathBuf) -> Result<(), String> {
-    let file = File::open(path).map_err(|e| format!("Failed to open file for sync: {}", e))?;
+async fn sync_file(path: &PathBuf) -> Result<(), String> {
+    let file = tokio::fs::File::open(path)
+        .await
+        .map_err(|e| format!("Failed to open file for sync: {}", e))?;
     file.sync_all()
+        .await
         .map_err(|e| format!("Failed to sync file: {}", e))
 }
 
 /// Write data to file with sync
-fn write_synced(path: &PathBuf, content: &str) -> Result<(), String> {
+async fn write_synced(path: &PathBuf, content: &str) -> Result<(), String> {
     // Ensure parent directory exists
     if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create directory: {}", e))?;
     }
 
     // Write to file
-    let mut file =
-        File::create(path).map_err(|e| format!("Failed to create file: {}", e))?;
+    let mut file = tokio::fs::File::create(path)
+        .await
+        .map_err(|e| format!("Failed to create file: {}", e))?;
     file.write_all(content.as_bytes())
+        .await
         .map_err(|e| format!("Failed to write file: {}", e))?;
 
-    // Sync to disk
+    // Durability barrier: don't let the caller proceed until the write is
+    // flushed to disk.
     file.sync_all()
+        .await
         .map_err(|e| format!("Failed to sync file: {}", e))?;
 
     Ok(())
 }
 
-/// Begin a new transaction
+/// Build the path of the record file for a given sequence number.
 ///
-/// Writes the WAL entry to disk BEFORE any actual operation begins.
-/// This ensures we can always recover if the operation is interrupted.
-pub fn begin_transaction(app: &tauri::AppHandle, operation: WalOperation) -> Result<String, String> {
-    let wal_path = get_wal_path(app)?;
-
-    // Check for existing transaction (shouldn't happen, but handle it)
-    if wal_path.exists() {
-        return Err(
-            "Cannot start new transaction: previous transaction still in progress. \
-             Please restart the application to recover."
-                .to_string(),
-        );
+/// Sequence numbers are zero-padded so that directory listings sort in
+/// append order without needing to parse every file name first.
+fn record_path(dir: &PathBuf, seq: u64) -> PathBuf {
+    dir.join(format!("{:020}.rec", seq))
+}
+
+/// Parse the sequence number out of a record file name, if it is one.
+fn parse_sequence(file_name: &std::ffi::OsStr) -> Option<u64> {
+    file_name.to_str()?.strip_suffix(".rec")?.parse().ok()
+}
+
+/// Determine the next sequence number to append, based on what is already
+/// on disk in `dir`.
+async fn next_sequence(dir: &PathBuf) -> Result<u64, String> {
+    if !path_exists(dir).await {
+        return Ok(0);
     }
 
-    let entry = WalEntry {
-        id: uuid::Uuid::new_v4().to_string(),
-        operation,
-        started_at: Utc::now().to_rfc3339(),
-        status: WalStatus::Pending,
-        error: None,
-    };
+    let mut max_seq = None;
+    let mut read_dir = tokio::fs::read_dir(dir)
+        .await
+        .map_err(|e| format!("Failed to read WAL directory: {}", e))?;
+    while let Some(entry) = read_dir
+        .next_entry()
+        .await
+        .map_err(|e| format!("Failed to read WAL directory entry: {}", e))?
+    {
+        if let Some(seq) = parse_sequence(&entry.file_name()) {
+            max_seq = Some(max_seq.map_or(seq, |m: u64| m.max(seq)));
+        }
+    }
 
-    // Serialize and write
-    let content = serde_json::to_string_pretty(&entry)
-        .map_err(|e| format!("Failed to serialize WAL entry: {}", e))?;
+    Ok(max_seq.map_or(0, |s| s + 1))
+}
 
-    write_synced(&wal_path, &content)?;
+/// Wrap a value's serialized JSON in an integrity envelope: a header line
+/// `<crc32-hex> <byte-len>\n` followed by exactly `byte-len` bytes of body.
+/// The header lets a reader validate the body before trusting it, instead
+/// of handing a possibly-torn write straight to `serde_json`. Used for WAL
+/// records as well as archived history entries.
+fn encode_envelope<T: Serialize>(value: &T) -> Result<String, String> {
+    let body = serde_json::to_string(value)
+        .map_err(|e| format!("Failed to serialize: {}", e))?;
+    let crc = crc32fast::hash(body.as_bytes());
 
-    Ok(entry.id)
+    Ok(format!("{:08x} {}\n{}", crc, body.len(), body))
 }
 
-/// Update the status of the current transaction
-pub fn update_status(app: &tauri::AppHandle, status: WalStatus) -> Result<(), String> {
-    let wal_path = get_wal_path(app)?;
+/// Parse and validate the single envelope at the start of `content`,
+/// returning the decoded value and whatever bytes remain after it (past
+/// the body's trailing newline, if any). Returns `None` if the envelope is
+/// malformed, the body is shorter than declared, or the CRC doesn't match
+/// — all of which indicate a torn or corrupted write that never durably
+/// completed.
+fn decode_envelope<T: for<'de> Deserialize<'de>>(content: &str) -> Option<(T, &str)> {
+    let (header, rest) = content.split_once('\n')?;
+    let mut parts = header.split(' ');
+    let crc_hex = parts.next()?;
+    let len: usize = parts.next()?.parse().ok()?;
+    let expected_crc = u32::from_str_radix(crc_hex, 16).ok()?;
 
-    if !wal_path.exists() {
-        return Ok(()); // No active transaction
+    // `len` comes straight from the (possibly corrupted) header, so it may
+    // not land on a UTF-8 char boundary; `get` returns `None` instead of
+    // panicking in that case.
+    let body = rest.get(..len)?;
+    let tail = rest.get(len..)?;
+
+    if crc32fast::hash(body.as_bytes()) != expected_crc {
+        return None; // corrupted write: bytes don't match their checksum
     }
 
-    let content =
-        fs::read_to_string(&wal_path).map_err(|e| format!("Failed to read WAL: {}", e))?;
+    let value = serde_json::from_str(body).ok()?;
+    let remainder = tail.strip_prefix('\n').unwrap_or(tail);
+    Some((value, remainder))
+}
 
-    let mut entry: WalEntry =
-        serde_json::from_str(&content).map_err(|e| format!("Failed to parse WAL: {}", e))?;
+fn encode_record(record: &WalRecord) -> Result<String, String> {
+    encode_envelope(record)
+}
 
-    entry.status = status;
+fn decode_record(content: &str) -> Option<WalRecord> {
+    decode_envelope(content).map(|(record, _)| record)
+}
 
-    let content = serde_json::to_string_pretty(&entry)
-        .map_err(|e| format!("Failed to serialize WAL entry: {}", e))?;
+/// Decode every envelope-wrapped value appended to a single multi-entry
+/// file, in append order. Like `read_records_at`, stops at the first
+/// envelope that fails to decode rather than erroring out, since only the
+/// tail of an append-only file can ever be torn.
+fn decode_envelope_stream<T: for<'de> Deserialize<'de>>(content: &str) -> Vec<T> {
+    let mut remaining = content;
+    let mut values = Vec::new();
 
-    write_synced(&wal_path, &content)?;
+    while !remaining.is_empty() {
+        match decode_envelope::<T>(remaining) {
+            Some((value, rest)) => {
+                values.push(value);
+                remaining = rest;
+            }
+            None => break,
+        }
+    }
 
-    Ok(())
+    values
 }
 
-/// Mark the current transaction as failed with an error
-pub fn mark_failed(app: &tauri::AppHandle, error_msg: &str) -> Result<(), String> {
-    let wal_path = get_wal_path(app)?;
+/// Append a single record to the log in `dir`, without acquiring
+/// [`WAL_LOCK`]. Callers that need atomicity across multiple appends (e.g.
+/// [`commit`], [`recover_incomplete`]) hold the lock themselves and call
+/// this directly to avoid deadlocking on a non-reentrant mutex.
+async fn append_record_at(dir: &PathBuf, record: &WalRecord) -> Result<(), String> {
+    tokio::fs::create_dir_all(dir)
+        .await
+        .map_err(|e| format!("Failed to create WAL directory: {}", e))?;
 
-    if !wal_path.exists() {
-        return Ok(());
+    let seq = next_sequence(dir).await?;
+    let path = record_path(dir, seq);
+    let content = encode_record(record)?;
+
+    write_synced(&path, &content).await
+}
+
+async fn read_records_at(dir: &PathBuf) -> Result<Vec<WalRecord>, String> {
+    Ok(read_record_files_at(dir)
+        .await?
+        .into_iter()
+        .map(|(_, record)| record)
+        .collect())
+}
+
+/// Read every record in `dir` along with the file it was decoded from, in
+/// append order.
+///
+/// Stops at the first record that fails to decode rather than erroring
+/// out, since an append-only log can only ever be torn at its tail (a
+/// crash mid-write never touches earlier, already-synced records). A lone
+/// `Begin` record that fails to decode means the transaction never safely
+/// began, so it is simply absent from the result rather than surfaced as
+/// an error.
+async fn read_record_files_at(dir: &PathBuf) -> Result<Vec<(PathBuf, WalRecord)>, String> {
+    if !path_exists(dir).await {
+        return Ok(Vec::new());
+    }
+
+    let mut entries = Vec::new();
+    let mut read_dir = tokio::fs::read_dir(dir)
+        .await
+        .map_err(|e| format!("Failed to read WAL directory: {}", e))?;
+    while let Some(entry) = read_dir
+        .next_entry()
+        .await
+        .map_err(|e| format!("Failed to read WAL directory entry: {}", e))?
+    {
+        if parse_sequence(&entry.file_name()).is_some() {
+            entries.push(entry);
+        }
     }
+    entries.sort_by_key(|e| e.file_name());
 
-    let content =
-        fs::read_to_string(&wal_path).map_err(|e| format!("Failed to read WAL: {}", e))?;
+    let mut records = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let content = match tokio::fs::read_to_string(entry.path()).await {
+            Ok(c) => c,
+            Err(_) => break,
+        };
+        match decode_record(&content) {
+            Some(record) => records.push((entry.path(), record)),
+            None => break, // torn/corrupted write; discard and stop
+        }
+    }
 
-    let mut entry: WalEntry =
-        serde_json::from_str(&content).map_err(|e| format!("Failed to parse WAL: {}", e))?;
+    Ok(records)
+}
 
-    entry.status = WalStatus::RolledBack;
-    entry.error = Some(error_msg.to_string());
+/// Append a record to the app's WAL log, serialized against other writers.
+async fn append_record(app: &tauri::AppHandle, record: &WalRecord) -> Result<(), String> {
+    let dir = get_wal_dir(app)?;
+    let _guard = WAL_LOCK.lock().await;
+    append_record_at(&dir, record).await
+}
 
-    let content = serde_json::to_string_pretty(&entry)
-        .map_err(|e| format!("Failed to serialize WAL entry: {}", e))?;
+/// Read every record currently in the app's WAL log, in append order.
+async fn read_records(app: &tauri::AppHandle) -> Result<Vec<WalRecord>, String> {
+    let dir = get_wal_dir(app)?;
+    read_records_at(&dir).await
+}
 
-    write_synced(&wal_path, &content)?;
+/// Fold a sequence of records into the latest known state of each
+/// transaction, in the order each transaction first began.
+fn reconstruct_entries(records: &[WalRecord]) -> Vec<WalEntry> {
+    let mut order: Vec<String> = Vec::new();
+    let mut entries: HashMap<String, WalEntry> = HashMap::new();
 
-    Ok(())
+    for record in records {
+        match record {
+            WalRecord::Begin {
+                txn_id,
+                operation,
+                started_at,
+            } => {
+                order.push(txn_id.clone());
+                entries.insert(
+                    txn_id.clone(),
+                    WalEntry {
+                        id: txn_id.clone(),
+                        operation: operation.clone(),
+                        started_at: started_at.clone(),
+                        status: WalStatus::Pending,
+                        error: None,
+                    },
+                );
+            }
+            WalRecord::StatusUpdate { txn_id, status } => {
+                if let Some(entry) = entries.get_mut(txn_id) {
+                    entry.status = status.clone();
+                }
+            }
+            WalRecord::Commit { txn_id } => {
+                if let Some(entry) = entries.get_mut(txn_id) {
+                    entry.status = WalStatus::Completed;
+                }
+            }
+            WalRecord::RolledBack { txn_id, error } => {
+                if let Some(entry) = entries.get_mut(txn_id) {
+                    entry.status = WalStatus::RolledBack;
+                    entry.error = error.clone();
+                }
+            }
+            WalRecord::Checkpoint {
+                txn_id,
+                processed_vault_ids,
+            } => {
+                if let Some(entry) = entries.get_mut(txn_id) {
+                    if let WalOperation::CleanupBrokenVaults {
+                        processed_vault_ids: progress,
+                        ..
+                    } = &mut entry.operation
+                    {
+                        *progress = processed_vault_ids.clone();
+                    }
+                }
+            }
+        }
+    }
+
+    order
+        .into_iter()
+        .filter_map(|id| entries.remove(&id))
+        .collect()
 }
 
-/// Commit the transaction - removes the WAL file
+/// Drop records belonging to transactions that have already reached a
+/// terminal state (`Completed`/`RolledBack`), keeping the log small.
+/// Records of transactions still in flight are left in place untouched.
 ///
-/// Should only be called after the operation has fully succeeded.
-pub fn commit(app: &tauri::AppHandle) -> Result<(), String> {
-    let wal_path = get_wal_path(app)?;
+/// Does not acquire [`WAL_LOCK`] itself; callers run this while already
+/// holding the lock so the read-then-remove isn't interleaved with a
+/// concurrent append.
+async fn compact_at(dir: &PathBuf) -> Result<(), String> {
+    let files = read_record_files_at(dir).await?;
 
-    if wal_path.exists() {
-        fs::remove_file(&wal_path).map_err(|e| format!("Failed to remove WAL file: {}", e))?;
+    let mut terminal: HashSet<String> = HashSet::new();
+    for (_, record) in &files {
+        match record {
+            WalRecord::Commit { txn_id } | WalRecord::RolledBack { txn_id, .. } => {
+                terminal.insert(txn_id.clone());
+            }
+            _ => {}
+        }
+    }
+
+    // Only ever remove the files backing terminal transactions. Records for
+    // transactions still in flight must never be deleted, even transiently
+    // -- a crash partway through compaction would otherwise destroy durable
+    // state for an operation that hasn't finished yet.
+    for (path, record) in files {
+        if terminal.contains(record.txn_id()) {
+            tokio::fs::remove_file(&path)
+                .await
+                .map_err(|e| format!("Failed to remove WAL record: {}", e))?;
+        }
     }
 
     Ok(())
 }
 
-/// Check for and recover from incomplete transactions
+/// Begin a new transaction
 ///
-/// Called on application startup. If an incomplete transaction is found,
-/// it will be rolled back by restoring the registry from the backup.
-pub fn recover_incomplete(app: &tauri::AppHandle) -> Result<RecoveryResult, String> {
-    let wal_path = get_wal_path(app)?;
+/// Appends a `Begin` record to the WAL BEFORE any actual operation begins.
+/// This ensures we can always recover if the operation is interrupted.
+/// Unlike a single rewritten WAL file, this does not block other
+/// transactions from starting concurrently.
+pub async fn begin_transaction(
+    app: &tauri::AppHandle,
+    operation: WalOperation,
+) -> Result<String, String> {
+    let txn_id = uuid::Uuid::new_v4().to_string();
 
-    if !wal_path.exists() {
-        return Ok(RecoveryResult {
-            recovered: false,
-            message: None,
-            operation_type: None,
-        });
+    let record = WalRecord::Begin {
+        txn_id: txn_id.clone(),
+        operation: operation.with_backup_hash(),
+        started_at: Utc::now().to_rfc3339(),
+    };
+
+    append_record(app, &record).await?;
+
+    Ok(txn_id)
+}
+
+/// Append a status update for the given transaction
+pub async fn update_status(
+    app: &tauri::AppHandle,
+    txn_id: &str,
+    status: WalStatus,
+) -> Result<(), String> {
+    append_record(
+        app,
+        &WalRecord::StatusUpdate {
+            txn_id: txn_id.to_string(),
+            status,
+        },
+    )
+    .await
+}
+
+/// Mark a transaction as rolled back with an error
+pub async fn mark_failed(
+    app: &tauri::AppHandle,
+    txn_id: &str,
+    error_msg: &str,
+) -> Result<(), String> {
+    clear_checkpoint_throttle(txn_id);
+
+    append_record(
+        app,
+        &WalRecord::RolledBack {
+            txn_id: txn_id.to_string(),
+            error: Some(error_msg.to_string()),
+        },
+    )
+    .await
+}
+
+/// Forget the last-checkpoint time for a transaction once it reaches a
+/// terminal state, so the throttle map doesn't grow unbounded.
+fn clear_checkpoint_throttle(txn_id: &str) {
+    LAST_CHECKPOINT.lock().unwrap().remove(txn_id);
+}
+
+/// Persist progress for a long-running operation (currently
+/// `CleanupBrokenVaults`), throttled to at most once per
+/// `config.interval` so large batches don't fsync on every single item.
+///
+/// On recovery, the last persisted `processed_vault_ids` lets rollback
+/// report (and, for callers that inspect it, restore) only the unfinished
+/// portion of the batch rather than treating the whole thing as reverted.
+pub async fn checkpoint(
+    app: &tauri::AppHandle,
+    txn_id: &str,
+    processed_vault_ids: Vec<String>,
+    config: CheckpointConfig,
+) -> Result<(), String> {
+    let due = {
+        let mut last = LAST_CHECKPOINT.lock().unwrap();
+        let now = std::time::Instant::now();
+        let due = last
+            .get(txn_id)
+            .map(|t| now.duration_since(*t) >= config.interval)
+            .unwrap_or(true);
+        if due {
+            last.insert(txn_id.to_string(), now);
+        }
+        due
+    };
+
+    if !due {
+        return Ok(());
     }
 
-    let content =
-        fs::read_to_string(&wal_path).map_err(|e| format!("Failed to read WAL: {}", e))?;
+    append_record(
+        app,
+        &WalRecord::Checkpoint {
+            txn_id: txn_id.to_string(),
+            processed_vault_ids,
+        },
+    )
+    .await
+}
 
-    let entry: WalEntry =
-        serde_json::from_str(&content).map_err(|e| format!("Failed to parse WAL: {}", e))?;
+/// Commit a transaction - appends a terminal `Commit` record and compacts
+/// any now-terminal records out of the log.
+///
+/// Should only be called after the operation has fully succeeded.
+pub async fn commit(app: &tauri::AppHandle, txn_id: &str) -> Result<(), String> {
+    clear_checkpoint_throttle(txn_id);
 
-    match entry.status {
-        WalStatus::Pending | WalStatus::InProgress => {
-            // Transaction was interrupted - rollback
-            let (recovered_msg, op_type) = rollback_operation(app, &entry)?;
+    let dir = get_wal_dir(app)?;
+    let _guard = WAL_LOCK.lock().await;
 
-            // Mark as rolled back and clean up
-            mark_failed(app, "Recovered from interrupted operation")?;
-            commit(app)?;
+    append_record_at(
+        &dir,
+        &WalRecord::Commit {
+            txn_id: txn_id.to_string(),
+        },
+    )
+    .await?;
 
-            Ok(RecoveryResult {
-                recovered: true,
-                message: Some(recovered_msg),
-                operation_type: Some(op_type),
-            })
+    // The durability barrier for "committed" is the fsynced `Commit` record
+    // append above, which has already succeeded by this point. Archiving and
+    // compaction are best-effort audit bookkeeping on top of that, not part
+    // of the commit's durability contract -- a transient failure writing to
+    // `history/` (disk full, permissions, ...) must not make an operation
+    // that already committed durably report back as a failed commit. Log
+    // and swallow errors from here on instead of propagating them.
+    match read_records_at(&dir).await {
+        Ok(records) => {
+            if let Some(mut entry) = reconstruct_entries(&records)
+                .into_iter()
+                .find(|e| e.id == txn_id)
+            {
+                entry.status = WalStatus::Completed;
+                if let Err(e) = archive_entry(app, &entry).await {
+                    eprintln!("WAL: failed to archive committed transaction {}: {}", txn_id, e);
+                }
+            }
         }
-        WalStatus::Completed | WalStatus::RolledBack => {
-            // Just clean up the WAL file
-            commit(app)?;
-
-            Ok(RecoveryResult {
-                recovered: false,
-                message: Some("Cleaned up completed transaction".to_string()),
-                operation_type: None,
-            })
+        Err(e) => {
+            eprintln!("WAL: failed to read records while archiving transaction {}: {}", txn_id, e);
         }
     }
+
+    if let Err(e) = compact_at(&dir).await {
+        eprintln!("WAL: failed to compact after committing transaction {}: {}", txn_id, e);
+    }
+
+    Ok(())
 }
 
-/// Rollback an operation by restoring from backup
-fn rollback_operation(app: &tauri::AppHandle, entry: &WalEntry) -> Result<(String, String), String> {
+/// Check for and recover from incomplete transactions
+///
+/// Called on application startup, `await`ed from Tauri's async `setup`
+/// hook so recovery never blocks the main thread. Every transaction whose
+/// latest record is `Pending`/`InProgress` with no matching
+/// `Commit`/`RolledBack` is rolled back independently; transactions that
+/// already reached a terminal state are left alone.
+pub async fn recover_incomplete(app: &tauri::AppHandle) -> Result<Vec<RecoveryResult>, String> {
+    let dir = get_wal_dir(app)?;
+    let _guard = WAL_LOCK.lock().await;
+
+    let records = read_records_at(&dir).await?;
+    let entries = reconstruct_entries(&records);
+
+    let mut results = Vec::new();
+
+    for entry in entries {
+        match entry.status {
+            WalStatus::Pending | WalStatus::InProgress => {
+                let (recovered_msg, op_type, verified) = rollback_operation(app, &entry).await?;
+
+                append_record_at(
+                    &dir,
+                    &WalRecord::RolledBack {
+                        txn_id: entry.id.clone(),
+                        error: Some("Recovered from interrupted operation".to_string()),
+                    },
+                )
+                .await?;
+
+                results.push(RecoveryResult {
+                    recovered: verified,
+                    message: Some(recovered_msg),
+                    operation_type: Some(op_type),
+                    integrity_failure: !verified,
+                });
+            }
+            WalStatus::Completed | WalStatus::RolledBack => {
+                // Already terminal; nothing to recover.
+            }
+        }
+    }
+
+    compact_at(&dir).await?;
+
+    Ok(results)
+}
+
+/// Rollback an operation by restoring from backup.
+///
+/// Returns `(message, operation_type, verified)`. `verified` is `false`
+/// when a registry backup failed its integrity check, in which case the
+/// restore is skipped entirely and the current, live registry is left
+/// untouched rather than risk clobbering it with a corrupted backup.
+async fn rollback_operation(
+    app: &tauri::AppHandle,
+    entry: &WalEntry,
+) -> Result<(String, String, bool), String> {
     match &entry.operation {
         WalOperation::DeleteVault {
             vault_id,
             registry_backup,
+            registry_backup_hash,
             ..
         } => {
+            if !backup_is_trustworthy(registry_backup, registry_backup_hash) {
+                return Ok((
+                    format!(
+                        "Registry backup for vault deletion {} failed its integrity check; \
+                         restore was skipped to avoid corrupting the vault list",
+                        vault_id
+                    ),
+                    "delete_vault".to_string(),
+                    false,
+                ));
+            }
+
             // Restore registry from backup
             let registry_path = get_registry_path(app)?;
-            write_synced(&registry_path, registry_backup)?;
+            write_synced(&registry_path, registry_backup).await?;
 
             Ok((
                 format!("Rolled back incomplete vault deletion: {}", vault_id),
                 "delete_vault".to_string(),
+                true,
             ))
         }
         WalOperation::CreateVault {
@@ -304,12 +927,15 @@ fn rollback_operation(app: &tauri::AppHandle, entry: &WalEntry) -> Result<(Strin
         } => {
             // Clean up partially created vault directory
             let path = PathBuf::from(vault_path);
-            if path.exists() {
+            if path_exists(&path).await {
                 // Only remove if it's empty or only contains Welcome.md
-                if let Ok(entries) = fs::read_dir(&path) {
-                    let count = entries.count();
+                if let Ok(mut entries) = tokio::fs::read_dir(&path).await {
+                    let mut count = 0;
+                    while let Ok(Some(_)) = entries.next_entry().await {
+                        count += 1;
+                    }
                     if count <= 1 {
-                        let _ = fs::remove_dir_all(&path);
+                        let _ = tokio::fs::remove_dir_all(&path).await;
                     }
                 }
             }
@@ -317,48 +943,353 @@ fn rollback_operation(app: &tauri::AppHandle, entry: &WalEntry) -> Result<(Strin
             Ok((
                 format!("Cleaned up incomplete vault creation: {}", vault_id),
                 "create_vault".to_string(),
+                true,
             ))
         }
         WalOperation::CleanupBrokenVaults {
             registry_backup,
+            registry_backup_hash,
             vault_ids,
+            processed_vault_ids,
         } => {
-            // Restore registry from backup
+            if !backup_is_trustworthy(registry_backup, registry_backup_hash) {
+                return Ok((
+                    "Registry backup for broken-vault cleanup failed its integrity check; \
+                     restore was skipped to avoid corrupting the vault list"
+                        .to_string(),
+                    "cleanup_broken".to_string(),
+                    false,
+                ));
+            }
+
+            // Restore only the unfinished portion of the batch: re-apply
+            // the checkpointed removals to the pre-operation backup so
+            // vaults already removed before the crash aren't resurrected.
+            let restored = apply_checkpoint_to_backup(registry_backup, processed_vault_ids);
             let registry_path = get_registry_path(app)?;
-            write_synced(&registry_path, registry_backup)?;
+            write_synced(&registry_path, &restored).await?;
 
+            let remaining = vault_ids.len().saturating_sub(processed_vault_ids.len());
             Ok((
                 format!(
-                    "Rolled back incomplete cleanup of {} broken vaults",
-                    vault_ids.len()
+                    "Rolled back incomplete cleanup of {} broken vaults ({} had already \
+                     been removed at the last checkpoint)",
+                    remaining,
+                    processed_vault_ids.len()
                 ),
                 "cleanup_broken".to_string(),
+                true,
             ))
         }
     }
 }
 
-/// Check if there's an active transaction
-pub fn has_active_transaction(app: &tauri::AppHandle) -> Result<bool, String> {
-    let wal_path = get_wal_path(app)?;
-    Ok(wal_path.exists())
+/// Build the path of the day's history file that `entry` should be
+/// appended to, based on when the transaction started.
+fn history_file_path(dir: &PathBuf, entry: &WalEntry) -> PathBuf {
+    let date = entry
+        .started_at
+        .get(0..10)
+        .unwrap_or("unknown-date");
+    dir.join(format!("{}.log", date))
+}
+
+/// Archive a completed transaction to the history directory and prune it
+/// down to the default retention policy.
+///
+/// One file per UTC day holds every entry whose transaction started that day, each
+/// wrapped in the same CRC envelope used for WAL records so a torn write
+/// is detected rather than corrupting the rest of the day's file.
+async fn archive_entry(app: &tauri::AppHandle, entry: &WalEntry) -> Result<(), String> {
+    let dir = get_history_dir(app)?;
+    archive_entry_at(&dir, entry).await?;
+    prune_history_at(&dir, HistoryRetention::default()).await
+}
+
+/// Directory-based core of [`archive_entry`], independent of `AppHandle` so
+/// it can be exercised directly in tests (mirroring [`append_record_at`]).
+async fn archive_entry_at(dir: &PathBuf, entry: &WalEntry) -> Result<(), String> {
+    tokio::fs::create_dir_all(dir)
+        .await
+        .map_err(|e| format!("Failed to create history directory: {}", e))?;
+
+    let path = history_file_path(dir, entry);
+    let envelope = encode_envelope(entry)?;
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .await
+        .map_err(|e| format!("Failed to open history file: {}", e))?;
+    file.write_all(envelope.as_bytes())
+        .await
+        .map_err(|e| format!("Failed to write history file: {}", e))?;
+    file.write_all(b"\n")
+        .await
+        .map_err(|e| format!("Failed to write history file: {}", e))?;
+    file.sync_all()
+        .await
+        .map_err(|e| format!("Failed to sync history file: {}", e))?;
+
+    Ok(())
+}
+
+/// List every archived, committed transaction, newest first: by the day the
+/// transaction started, then by archive order within that day.
+pub async fn list_history(app: &tauri::AppHandle) -> Result<Vec<WalEntry>, String> {
+    list_history_at(&get_history_dir(app)?).await
+}
+
+/// Directory-based core of [`list_history`].
+async fn list_history_at(dir: &PathBuf) -> Result<Vec<WalEntry>, String> {
+    if !path_exists(dir).await {
+        return Ok(Vec::new());
+    }
+
+    let mut file_names = Vec::new();
+    let mut read_dir = tokio::fs::read_dir(dir)
+        .await
+        .map_err(|e| format!("Failed to read history directory: {}", e))?;
+    while let Some(entry) = read_dir
+        .next_entry()
+        .await
+        .map_err(|e| format!("Failed to read history directory entry: {}", e))?
+    {
+        if entry.file_name().to_string_lossy().ends_with(".log") {
+            file_names.push(entry.file_name());
+        }
+    }
+    file_names.sort();
+
+    let mut entries = Vec::new();
+    for file_name in file_names {
+        let content = tokio::fs::read_to_string(dir.join(&file_name))
+            .await
+            .map_err(|e| format!("Failed to read history file: {}", e))?;
+        entries.extend(decode_envelope_stream::<WalEntry>(&content));
+    }
+
+    entries.reverse(); // most recent first
+    Ok(entries)
+}
+
+/// Prune the history archive down to `retention`: entries older than
+/// `max_age_days` are dropped, then the oldest remaining entries are
+/// dropped until at most `max_entries` are left.
+pub async fn prune_history(
+    app: &tauri::AppHandle,
+    retention: HistoryRetention,
+) -> Result<(), String> {
+    prune_history_at(&get_history_dir(app)?, retention).await
 }
 
-/// Get the current WAL entry if one exists
-pub fn get_current_entry(app: &tauri::AppHandle) -> Result<Option<WalEntry>, String> {
-    let wal_path = get_wal_path(app)?;
+/// Directory-based core of [`prune_history`].
+///
+/// Whole day-files that have aged out, or that fall entirely within the
+/// dropped `max_entries` range, are removed outright. A day-file that is
+/// only partially over the `max_entries` limit is rewritten in place with
+/// its surviving, oldest-first suffix. Every other file -- including any
+/// not touched at all -- is left alone, so a crash mid-prune can only
+/// ever cost the one file actually being trimmed, never the rest of the
+/// archive.
+async fn prune_history_at(dir: &PathBuf, retention: HistoryRetention) -> Result<(), String> {
+    if !path_exists(dir).await {
+        return Ok(());
+    }
+
+    let cutoff = (Utc::now() - chrono::Duration::days(retention.max_age_days))
+        .format("%Y-%m-%d")
+        .to_string();
+
+    let mut file_names = Vec::new();
+    let mut read_dir = tokio::fs::read_dir(dir)
+        .await
+        .map_err(|e| format!("Failed to read history directory: {}", e))?;
+    while let Some(entry) = read_dir
+        .next_entry()
+        .await
+        .map_err(|e| format!("Failed to read history directory entry: {}", e))?
+    {
+        let file_name = entry.file_name();
+        if file_name.to_string_lossy().ends_with(".log") {
+            file_names.push(file_name);
+        }
+    }
+    file_names.sort();
+
+    // Drop whole days that have aged out entirely.
+    for file_name in &file_names {
+        let day = file_name.to_string_lossy();
+        let day = day.trim_end_matches(".log");
+        if day < cutoff.as_str() {
+            let _ = tokio::fs::remove_file(dir.join(file_name)).await;
+        }
+    }
+
+    // Re-read what's left, per file and oldest-first within each file (the
+    // order entries were archived in), and trim down to `max_entries`.
+    let mut remaining_file_names = Vec::new();
+    let mut read_dir = tokio::fs::read_dir(dir)
+        .await
+        .map_err(|e| format!("Failed to read history directory: {}", e))?;
+    while let Some(entry) = read_dir
+        .next_entry()
+        .await
+        .map_err(|e| format!("Failed to read history directory entry: {}", e))?
+    {
+        let file_name = entry.file_name();
+        if file_name.to_string_lossy().ends_with(".log") {
+            remaining_file_names.push(file_name);
+        }
+    }
+    remaining_file_names.sort();
+
+    let mut per_file = Vec::with_capacity(remaining_file_names.len());
+    let mut total = 0usize;
+    for file_name in &remaining_file_names {
+        let content = tokio::fs::read_to_string(dir.join(file_name))
+            .await
+            .map_err(|e| format!("Failed to read history file: {}", e))?;
+        let entries = decode_envelope_stream::<WalEntry>(&content);
+        total += entries.len();
+        per_file.push((file_name.clone(), entries));
+    }
+
+    if total <= retention.max_entries {
+        return Ok(());
+    }
+
+    // Drop the oldest entries, file by file, starting from the oldest day.
+    let mut drop_count = total - retention.max_entries;
+    for (file_name, entries) in per_file {
+        if drop_count == 0 {
+            break;
+        }
+        if drop_count >= entries.len() {
+            drop_count -= entries.len();
+            let _ = tokio::fs::remove_file(dir.join(&file_name)).await;
+            continue;
+        }
+
+        let kept = &entries[drop_count..];
+        drop_count = 0;
+
+        let mut content = String::new();
+        for entry in kept {
+            content.push_str(&encode_envelope(entry)?);
+            content.push('\n');
+        }
+        write_synced(&dir.join(&file_name), &content).await?;
+    }
+
+    Ok(())
+}
+
+/// Restore the registry to its state just before a completed, archived
+/// transaction — e.g. undo an accidental `DeleteVault` after the fact.
+///
+/// Uses the same hash-verification as crash recovery: a backup that fails
+/// its integrity check is never restored.
+pub async fn restore_from_history(
+    app: &tauri::AppHandle,
+    txn_id: &str,
+) -> Result<RecoveryResult, String> {
+    let entry = list_history(app)
+        .await?
+        .into_iter()
+        .find(|e| e.id == txn_id)
+        .ok_or_else(|| format!("No history entry found for transaction {}", txn_id))?;
+
+    match &entry.operation {
+        WalOperation::DeleteVault {
+            vault_id,
+            registry_backup,
+            registry_backup_hash,
+            ..
+        } => restore_registry_backup(
+            app,
+            "delete_vault",
+            &format!("Restored registry to its state before deleting vault {}", vault_id),
+            registry_backup,
+            registry_backup_hash,
+        )
+        .await,
+        WalOperation::CleanupBrokenVaults {
+            registry_backup,
+            registry_backup_hash,
+            vault_ids,
+            ..
+        } => {
+            restore_registry_backup(
+                app,
+                "cleanup_broken",
+                &format!(
+                    "Restored registry to its state before cleaning up {} broken vaults",
+                    vault_ids.len()
+                ),
+                registry_backup,
+                registry_backup_hash,
+            )
+            .await
+        }
+        WalOperation::CreateVault { .. } => {
+            Err("Vault creation has no registry backup to restore from history".to_string())
+        }
+    }
+}
 
-    if !wal_path.exists() {
-        return Ok(None);
+/// Shared restore path for history entries that carry a registry backup:
+/// verify it, then restore it, surfacing an `integrity_failure` result
+/// instead of an error if the backup can't be trusted.
+async fn restore_registry_backup(
+    app: &tauri::AppHandle,
+    operation_type: &str,
+    success_message: &str,
+    registry_backup: &str,
+    registry_backup_hash: &str,
+) -> Result<RecoveryResult, String> {
+    if !backup_is_trustworthy(registry_backup, registry_backup_hash) {
+        return Ok(RecoveryResult {
+            recovered: false,
+            message: Some(
+                "Archived registry backup failed its integrity check; restore was skipped"
+                    .to_string(),
+            ),
+            operation_type: Some(operation_type.to_string()),
+            integrity_failure: true,
+        });
     }
 
-    let content =
-        fs::read_to_string(&wal_path).map_err(|e| format!("Failed to read WAL: {}", e))?;
+    let registry_path = get_registry_path(app)?;
+    write_synced(&registry_path, registry_backup).await?;
+
+    Ok(RecoveryResult {
+        recovered: true,
+        message: Some(success_message.to_string()),
+        operation_type: Some(operation_type.to_string()),
+        integrity_failure: false,
+    })
+}
+
+/// List the ids of all transactions that are still in flight
+pub async fn has_active_transaction(app: &tauri::AppHandle) -> Result<Vec<String>, String> {
+    Ok(get_current_entry(app)
+        .await?
+        .into_iter()
+        .map(|e| e.id)
+        .collect())
+}
 
-    let entry: WalEntry =
-        serde_json::from_str(&content).map_err(|e| format!("Failed to parse WAL: {}", e))?;
+/// Get every WAL entry that is still in flight (not yet committed or
+/// rolled back)
+pub async fn get_current_entry(app: &tauri::AppHandle) -> Result<Vec<WalEntry>, String> {
+    let records = read_records(app).await?;
 
-    Ok(Some(entry))
+    Ok(reconstruct_entries(&records)
+        .into_iter()
+        .filter(|e| matches!(e.status, WalStatus::Pending | WalStatus::InProgress))
+        .collect())
 }
 
 #[cfg(test)]
@@ -369,6 +1300,304 @@ mod tests {
     // In a real implementation, we'd test:
     // - Transaction lifecycle (begin -> update -> commit)
     // - Recovery after simulated crash
-    // - Concurrent transaction prevention
+    // - Concurrent transaction recovery
     // - Registry backup/restore
+
+    fn temp_wal_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("dubun-wal-test-{}-{}", name, uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn torn_trailing_record_is_discarded_as_clean() {
+        let dir = temp_wal_dir("torn-trailing-record");
+
+        let record = WalRecord::Begin {
+            txn_id: "txn-1".to_string(),
+            operation: WalOperation::CreateVault {
+                vault_id: "v1".to_string(),
+                vault_path: "/tmp/v1".to_string(),
+            },
+            started_at: Utc::now().to_rfc3339(),
+        };
+        append_record_at(&dir, &record).await.unwrap();
+
+        // Simulate a crash mid-write by truncating the last few bytes of
+        // the record file, as if the body had only been partially flushed.
+        let path = record_path(&dir, 0);
+        let content = std::fs::read_to_string(&path).unwrap();
+        let truncated = &content[..content.len() - 5];
+        std::fs::write(&path, truncated).unwrap();
+
+        let records = read_records_at(&dir).await.unwrap();
+        assert!(
+            records.is_empty(),
+            "a torn record must be discarded, not surfaced as a parse error"
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn valid_record_round_trips_through_the_crc_envelope() {
+        let dir = temp_wal_dir("valid-record");
+
+        let record = WalRecord::Commit {
+            txn_id: "txn-2".to_string(),
+        };
+        append_record_at(&dir, &record).await.unwrap();
+
+        let records = read_records_at(&dir).await.unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].txn_id(), "txn-2");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn corrupted_registry_backup_is_not_trusted() {
+        let backup = r#"{"vaults":[]}"#;
+        let hash = sha256_hex(backup);
+
+        assert!(backup_is_trustworthy(backup, &hash));
+        assert!(!backup_is_trustworthy("not json", &hash));
+        assert!(!backup_is_trustworthy(backup, "0000deadbeef"));
+    }
+
+    #[tokio::test]
+    async fn compact_never_deletes_records_for_a_still_in_flight_transaction() {
+        let dir = temp_wal_dir("compact-keeps-in-flight");
+
+        // txn-done: fully terminated, should be compacted away.
+        append_record_at(
+            &dir,
+            &WalRecord::Begin {
+                txn_id: "txn-done".to_string(),
+                operation: WalOperation::CreateVault {
+                    vault_id: "v1".to_string(),
+                    vault_path: "/tmp/v1".to_string(),
+                },
+                started_at: Utc::now().to_rfc3339(),
+            },
+        )
+        .await
+        .unwrap();
+        append_record_at(
+            &dir,
+            &WalRecord::Commit {
+                txn_id: "txn-done".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+
+        // txn-live: still in flight, must survive compaction untouched.
+        append_record_at(
+            &dir,
+            &WalRecord::Begin {
+                txn_id: "txn-live".to_string(),
+                operation: WalOperation::CreateVault {
+                    vault_id: "v2".to_string(),
+                    vault_path: "/tmp/v2".to_string(),
+                },
+                started_at: Utc::now().to_rfc3339(),
+            },
+        )
+        .await
+        .unwrap();
+
+        compact_at(&dir).await.unwrap();
+
+        let records = read_records_at(&dir).await.unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].txn_id(), "txn-live");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn a_corrupted_length_that_splits_a_utf8_char_is_rejected_not_panicked() {
+        // Craft an envelope whose declared length lands one byte inside a
+        // multi-byte UTF-8 character instead of on a char boundary.
+        let body = r#"{"Commit":{"txn_id":"café"}}"#;
+        let bad_len = body.find('é').unwrap() + 1; // splits the 'é'
+        let content = format!("{:08x} {}\n{}", crc32fast::hash(body.as_bytes()), bad_len, body);
+
+        assert!(decode_record(&content).is_none());
+    }
+
+    #[test]
+    fn rollback_restores_only_the_unfinished_tail_of_a_checkpointed_cleanup() {
+        let backup = serde_json::json!([
+            {"id": "broken-1"},
+            {"id": "broken-2"},
+            {"id": "healthy"},
+        ])
+        .to_string();
+
+        let restored = apply_checkpoint_to_backup(&backup, &["broken-1".to_string()]);
+        let restored: serde_json::Value = serde_json::from_str(&restored).unwrap();
+        let ids: Vec<&str> = restored
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v["id"].as_str().unwrap())
+            .collect();
+
+        assert_eq!(
+            ids,
+            vec!["broken-2", "healthy"],
+            "the already-checkpointed removal of broken-1 must not be undone"
+        );
+    }
+
+    #[test]
+    fn reconstruct_entries_folds_the_latest_checkpoint_into_progress() {
+        let records = vec![
+            WalRecord::Begin {
+                txn_id: "txn-cleanup".to_string(),
+                operation: WalOperation::CleanupBrokenVaults {
+                    registry_backup: "[]".to_string(),
+                    registry_backup_hash: sha256_hex("[]"),
+                    vault_ids: vec!["v1".to_string(), "v2".to_string(), "v3".to_string()],
+                    processed_vault_ids: Vec::new(),
+                },
+                started_at: Utc::now().to_rfc3339(),
+            },
+            WalRecord::Checkpoint {
+                txn_id: "txn-cleanup".to_string(),
+                processed_vault_ids: vec!["v1".to_string()],
+            },
+            WalRecord::Checkpoint {
+                txn_id: "txn-cleanup".to_string(),
+                processed_vault_ids: vec!["v1".to_string(), "v2".to_string()],
+            },
+        ];
+
+        let entries = reconstruct_entries(&records);
+        let entry = entries.iter().find(|e| e.id == "txn-cleanup").unwrap();
+        match &entry.operation {
+            WalOperation::CleanupBrokenVaults {
+                processed_vault_ids,
+                ..
+            } => {
+                assert_eq!(processed_vault_ids, &vec!["v1".to_string(), "v2".to_string()]);
+            }
+            _ => panic!("expected CleanupBrokenVaults"),
+        }
+    }
+
+    #[tokio::test]
+    async fn archived_entries_survive_pruning_of_unrelated_days() {
+        let dir = temp_wal_dir("history-archive");
+
+        let old_entry = WalEntry {
+            id: "txn-old".to_string(),
+            operation: WalOperation::CreateVault {
+                vault_id: "v1".to_string(),
+                vault_path: "/tmp/v1".to_string(),
+            },
+            started_at: (Utc::now() - chrono::Duration::days(200)).to_rfc3339(),
+            status: WalStatus::Completed,
+            error: None,
+        };
+        let recent_entry = WalEntry {
+            id: "txn-recent".to_string(),
+            operation: WalOperation::CreateVault {
+                vault_id: "v2".to_string(),
+                vault_path: "/tmp/v2".to_string(),
+            },
+            started_at: Utc::now().to_rfc3339(),
+            status: WalStatus::Completed,
+            error: None,
+        };
+
+        archive_entry_at(&dir, &old_entry).await.unwrap();
+        archive_entry_at(&dir, &recent_entry).await.unwrap();
+
+        let retention = HistoryRetention {
+            max_entries: 500,
+            max_age_days: 90,
+        };
+        prune_history_at(&dir, retention).await.unwrap();
+
+        let remaining = list_history_at(&dir).await.unwrap();
+        let ids: Vec<&str> = remaining.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec!["txn-recent"],
+            "pruning an aged-out day must not disturb entries in other, untouched day files"
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn prune_by_max_entries_rewrites_the_partially_dropped_day_file() {
+        let dir = temp_wal_dir("history-prune-boundary");
+
+        // All archived within the same few minutes, so they land in the
+        // same day file and pruning has to trim -- not just delete -- it.
+        for i in 0..3 {
+            let entry = WalEntry {
+                id: format!("txn-{}", i),
+                operation: WalOperation::CreateVault {
+                    vault_id: format!("v{}", i),
+                    vault_path: format!("/tmp/v{}", i),
+                },
+                started_at: (Utc::now() - chrono::Duration::minutes(2 - i)).to_rfc3339(),
+                status: WalStatus::Completed,
+                error: None,
+            };
+            archive_entry_at(&dir, &entry).await.unwrap();
+        }
+
+        let retention = HistoryRetention {
+            max_entries: 1,
+            max_age_days: 90,
+        };
+        prune_history_at(&dir, retention).await.unwrap();
+
+        let remaining = list_history_at(&dir).await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].id, "txn-2", "the most recently archived entry must survive");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn wal_lock_serializes_concurrent_async_appends_into_distinct_records() {
+        let dir = temp_wal_dir("concurrent-appends");
+
+        // Guard each append with WAL_LOCK, exactly as append_record() does
+        // for its AppHandle-based callers: this is what actually prevents
+        // two concurrent writers from reading the same "next sequence
+        // number" and racing to write it.
+        let writers = (0..8).map(|i| {
+            let dir = dir.clone();
+            tokio::spawn(async move {
+                let _guard = WAL_LOCK.lock().await;
+                append_record_at(
+                    &dir,
+                    &WalRecord::Commit {
+                        txn_id: format!("txn-{}", i),
+                    },
+                )
+                .await
+            })
+        });
+        for writer in writers {
+            writer.await.unwrap().unwrap();
+        }
+
+        let records = read_records_at(&dir).await.unwrap();
+        assert_eq!(
+            records.len(),
+            8,
+            "every concurrent, lock-guarded append must land in its own record"
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
 }